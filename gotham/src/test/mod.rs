@@ -5,20 +5,29 @@
 use std::fmt;
 use std::net::{self, IpAddr, SocketAddr};
 use std::ops::{Deref, DerefMut};
+use std::result::Result as StdResult;
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
+use base64;
 use failure;
 
-use futures::{future, Future, Stream};
+use futures::{future, stream, Future, Stream};
 use futures_timer::Delay;
 use hyper::client::{
     connect::{Connect, Connected, Destination},
     Client,
 };
-use hyper::header::CONTENT_TYPE;
-use hyper::{Body, Method, Request, Response, Uri};
+use hyper::upgrade::Upgraded;
+use hyper::header::{
+    CONNECTION, CONTENT_LENGTH, CONTENT_TYPE, LOCATION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY,
+    SEC_WEBSOCKET_VERSION, TRANSFER_ENCODING, UPGRADE,
+};
+use hyper::{Body, Method, Request, Response, StatusCode, Uri};
 use mime;
+use rand;
+use sha1::Sha1;
+use tokio::io::{read_exact, write_all};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::runtime::Runtime;
 
@@ -126,47 +135,118 @@ impl TestServer {
         // We're creating a private TCP-based pipe here. Bind to an ephemeral port, connect to
         // it and then immediately discard the listener.
 
-        let client = Client::builder().build(TestConnect {
+        Ok(self.client_with_connector(TestConnect {
             addr: self.data.addr,
-        });
+        }))
+    }
 
-        Ok(TestClient {
+    /// Returns a client that reaches the `TestServer` through a caller-supplied `Connect`
+    /// implementation, rather than the default bare-`TcpStream` transport.
+    ///
+    /// This allows the harness to exercise code paths that depend on the connection metadata a
+    /// real client would report — for example a rustls or native-tls wrapper serving the handler
+    /// over HTTPS, or a connector that advertises ALPN and reports `Connected::negotiated_h2()` so
+    /// the HTTP/2 paths are driven.
+    pub fn client_with_connector<C>(&self, connector: C) -> TestClient<C>
+    where
+        C: Connect + Clone + Send + Sync + 'static,
+        C::Transport: 'static,
+        C::Future: 'static,
+    {
+        let client = Client::builder().build(connector);
+
+        TestClient {
             client,
             test_server: self.clone(),
-        })
+            max_redirects: 0,
+        }
     }
 
     /// Runs the event loop until the response future is completed.
     ///
-    /// If the future came from a different instance of `TestServer`, the event loop will run until
-    /// the timeout is triggered.
-    fn run_request<F>(&mut self, f: F) -> Result<F::Item>
+    /// A request that outlives the configured timeout resolves to `TestRequestError::Timeout`; any
+    /// error produced by the request future itself is classified by its own `Into<TestRequestError>`
+    /// conversion, preserving the underlying cause.
+    fn run_request<F>(&mut self, f: F) -> StdResult<F::Item, TestRequestError>
     where
         F: Future + Send + 'static,
-        F::Error: failure::Fail + Sized,
         F::Item: Send,
+        F::Error: Into<TestRequestError> + Send,
     {
         let timeout_duration = Duration::from_secs(self.data.timeout);
         let timeout = Delay::new(timeout_duration);
-        let might_expire = self.run_future(f.select2(timeout).map_err(|either| {
-            let e: failure::Error = match either {
-                future::Either::A((req_err, _)) => {
-                    warn!("run_request request error: {:?}", req_err);
-                    req_err.into()
-                }
-                future::Either::B((times_up, _)) => {
-                    warn!("run_request timed out");
-                    times_up.into()
-                }
-            };
-            e.compat()
-        }))?;
 
-        match might_expire {
-            future::Either::A((item, _)) => Ok(item),
-            future::Either::B(_) => Err(failure::err_msg("timed out")),
+        let result = self
+            .data
+            .runtime
+            .write()
+            .unwrap()
+            .block_on(f.select2(timeout));
+
+        match result {
+            Ok(future::Either::A((item, _))) => Ok(item),
+            Ok(future::Either::B(_)) => {
+                warn!("run_request timed out");
+                Err(TestRequestError::timeout(timeout_duration))
+            }
+            Err(future::Either::A((req_err, _))) => {
+                let err = req_err.into();
+                warn!("run_request request error: {:?}", err);
+                Err(err)
+            }
+            Err(future::Either::B((times_up, _))) => {
+                Err(TestRequestError::other(times_up.into()))
+            }
+        }
+    }
+    /// Drives a batch of request futures to completion concurrently on the shared runtime.
+    ///
+    /// All of the futures are combined with `future::join_all` and driven under a single
+    /// `block_on`, so they make progress against the handler simultaneously rather than being
+    /// serialized one request at a time. The responses are returned in submission order. If any
+    /// future fails the whole batch fails, and the `TestServer` timeout applies to the batch as a
+    /// whole.
+    pub fn run_requests<I>(&self, futures: I) -> StdResult<Vec<TestResponse>, TestRequestError>
+    where
+        I: IntoIterator,
+        I::Item: Future<Item = Response<Body>> + Send + 'static,
+        <I::Item as Future>::Error: Into<TestRequestError> + Send,
+    {
+        let timeout_duration = Duration::from_secs(self.data.timeout);
+        let timeout = Delay::new(timeout_duration);
+        let joined = future::join_all(futures.into_iter().collect::<Vec<_>>());
+
+        let result = self
+            .data
+            .runtime
+            .write()
+            .unwrap()
+            .block_on(joined.select2(timeout));
+
+        match result {
+            Ok(future::Either::A((responses, _))) => Ok(responses
+                .into_iter()
+                .map(|response| TestResponse {
+                    response,
+                    reader: Box::new(self.clone()),
+                    redirects: Vec::new(),
+                })
+                .collect()),
+            Ok(future::Either::B(_)) => {
+                warn!("run_requests timed out");
+                Err(TestRequestError::timeout(timeout_duration))
+            }
+            Err(future::Either::A((req_err, _))) => {
+                let err = req_err.into();
+                warn!("run_requests request error: {:?}", err);
+                Err(err)
+            }
+            Err(future::Either::B((times_up, _))) => {
+                Err(TestRequestError::other(times_up.into()))
+            }
         }
     }
+
     /// Runs a future inside of the internal runtime.
     ///
     /// This blocks on the result of the future and behaves like a synchronous
@@ -189,45 +269,100 @@ impl TestServer {
     }
 }
 
+/// The default 64 MiB ceiling applied when buffering a response body.
+const MAX_BODY_SIZE: usize = 64 * 1024 * 1024;
+
 impl BodyReader for TestServer {
-    fn read_body(&mut self, response: Response<Body>) -> Result<Vec<u8>> {
+    fn read_body(&mut self, response: Response<Body>) -> StdResult<Vec<u8>, TestRequestError> {
+        self.read_body_with_limit(response, MAX_BODY_SIZE)
+    }
+
+    fn read_body_with_limit(
+        &mut self,
+        response: Response<Body>,
+        max_bytes: usize,
+    ) -> StdResult<Vec<u8>, TestRequestError> {
+        let f = response
+            .into_body()
+            .map_err(|e| TestRequestError::body_read(e.into()))
+            .fold(Vec::new(), move |mut acc, chunk| {
+                if acc.len() + chunk.len() > max_bytes {
+                    future::err(TestRequestError::body_too_large(max_bytes))
+                } else {
+                    acc.extend_from_slice(&chunk);
+                    future::ok(acc)
+                }
+            });
+        self.data.runtime.write().unwrap().block_on(f)
+    }
+
+    fn read_body_chunks(
+        &mut self,
+        response: Response<Body>,
+    ) -> StdResult<Vec<Vec<u8>>, TestRequestError> {
         let f = response
             .into_body()
-            .concat2()
-            .map(|chunk| chunk.into_iter().collect());
-        self.run_future(f)
+            .map_err(|e| TestRequestError::body_read(e.into()))
+            .fold((0usize, Vec::new()), |(total, mut acc), chunk| {
+                let total = total + chunk.len();
+                if total > MAX_BODY_SIZE {
+                    future::err(TestRequestError::body_too_large(MAX_BODY_SIZE))
+                } else {
+                    acc.push(chunk.to_vec());
+                    future::ok((total, acc))
+                }
+            });
+        self.data
+            .runtime
+            .write()
+            .unwrap()
+            .block_on(f)
+            .map(|(_, chunks)| chunks)
     }
 }
 
 /// Client interface for issuing requests to a `TestServer`.
-pub struct TestClient {
-    client: Client<TestConnect, Body>,
+///
+/// The client is generic over the `Connect` implementation used to reach the server, defaulting to
+/// the internal `TestConnect` transport. Use `TestServer::client_with_connector` to substitute a
+/// TLS/ALPN-aware or otherwise custom connector.
+pub struct TestClient<C = TestConnect>
+where
+    C: Connect,
+{
+    client: Client<C, Body>,
     test_server: TestServer,
+    max_redirects: usize,
 }
 
-impl TestClient {
+impl<C> TestClient<C>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+    C::Transport: 'static,
+    C::Future: 'static,
+{
     /// Parse the URI and begin constructing a HEAD request using this `TestClient`.
-    pub fn head(self, uri: &str) -> RequestBuilder {
+    pub fn head(self, uri: &str) -> RequestBuilder<C> {
         self.build_request(Method::HEAD, uri)
     }
 
     /// Begin constructing a HEAD request using this `TestClient`.
-    pub fn head_uri(self, uri: Uri) -> RequestBuilder {
+    pub fn head_uri(self, uri: Uri) -> RequestBuilder<C> {
         self.build_request_uri(Method::HEAD, uri)
     }
 
     /// Parse the URI and begin constructing a GET request using this `TestClient`.
-    pub fn get(self, uri: &str) -> RequestBuilder {
+    pub fn get(self, uri: &str) -> RequestBuilder<C> {
         self.build_request(Method::GET, uri)
     }
 
     /// Begin constructing a GET request using this `TestClient`.
-    pub fn get_uri(self, uri: Uri) -> RequestBuilder {
+    pub fn get_uri(self, uri: Uri) -> RequestBuilder<C> {
         self.build_request_uri(Method::GET, uri)
     }
 
     /// Parse the URI and begin constructing a POST request using this `TestClient`.
-    pub fn post<T>(self, uri: &str, body: T, content_type: mime::Mime) -> RequestBuilder
+    pub fn post<T>(self, uri: &str, body: T, content_type: mime::Mime) -> RequestBuilder<C>
     where
         T: Into<Body>,
     {
@@ -237,7 +372,7 @@ impl TestClient {
     }
 
     /// Begin constructing a POST request using this `TestClient`.
-    pub fn post_uri<T>(self, uri: Uri, body: T, content_type: mime::Mime) -> RequestBuilder
+    pub fn post_uri<T>(self, uri: Uri, body: T, content_type: mime::Mime) -> RequestBuilder<C>
     where
         T: Into<Body>,
     {
@@ -247,7 +382,7 @@ impl TestClient {
     }
 
     /// Parse the URI and begin constructing a PUT request using this `TestClient`.
-    pub fn put<T>(self, uri: &str, body: T, content_type: mime::Mime) -> RequestBuilder
+    pub fn put<T>(self, uri: &str, body: T, content_type: mime::Mime) -> RequestBuilder<C>
     where
         T: Into<Body>,
     {
@@ -257,7 +392,7 @@ impl TestClient {
     }
 
     /// Begin constructing a PUT request using this `TestClient`.
-    pub fn put_uri<T>(self, uri: Uri, body: T, content_type: mime::Mime) -> RequestBuilder
+    pub fn put_uri<T>(self, uri: Uri, body: T, content_type: mime::Mime) -> RequestBuilder<C>
     where
         T: Into<Body>,
     {
@@ -267,7 +402,7 @@ impl TestClient {
     }
 
     /// Parse the URI and begin constructing a PATCH request using this `TestClient`.
-    pub fn patch<T>(self, uri: &str, body: T, content_type: mime::Mime) -> RequestBuilder
+    pub fn patch<T>(self, uri: &str, body: T, content_type: mime::Mime) -> RequestBuilder<C>
     where
         T: Into<Body>,
     {
@@ -277,7 +412,7 @@ impl TestClient {
     }
 
     /// Begin constructing a PATCH request using this `TestClient`.
-    pub fn patch_uri<T>(self, uri: Uri, body: T, content_type: mime::Mime) -> RequestBuilder
+    pub fn patch_uri<T>(self, uri: Uri, body: T, content_type: mime::Mime) -> RequestBuilder<C>
     where
         T: Into<Body>,
     {
@@ -287,45 +422,403 @@ impl TestClient {
     }
 
     /// Parse the URI and begin constructing a DELETE request using this `TestClient`.
-    pub fn delete(self, uri: &str) -> RequestBuilder {
+    pub fn delete(self, uri: &str) -> RequestBuilder<C> {
         self.build_request(Method::DELETE, uri)
     }
 
     /// Begin constructing a DELETE request using this `TestClient`.
-    pub fn delete_uri(self, uri: Uri) -> RequestBuilder {
+    pub fn delete_uri(self, uri: Uri) -> RequestBuilder<C> {
         self.build_request_uri(Method::DELETE, uri)
     }
 
     /// Parse the URI and begin constructing a request with the given HTTP method.
-    pub fn build_request(self, method: Method, uri: &str) -> RequestBuilder {
+    pub fn build_request(self, method: Method, uri: &str) -> RequestBuilder<C> {
         RequestBuilder::new(self, method, uri.parse().unwrap())
     }
 
     /// Begin constructing a request with the given HTTP method and Uri.
-    pub fn build_request_uri(self, method: Method, uri: Uri) -> RequestBuilder {
+    pub fn build_request_uri(self, method: Method, uri: Uri) -> RequestBuilder<C> {
         RequestBuilder::new(self, method, uri)
     }
 
+    /// Enables redirect following for requests issued through this `TestClient`.
+    ///
+    /// Once enabled, `perform` inspects each `301`, `302`, `303`, `307` or `308` response (other
+    /// `3xx` statuses are returned as they are), resolves its `Location` header against
+    /// the current request URI and re-issues the request on the internal runtime, up to `max`
+    /// hops. A `303 See Other` redirect is downgraded to a bodyless `GET`, while `307`/`308`
+    /// preserve the original method and body. Reaching the hop limit returns
+    /// `TestRequestError::TooManyRedirects`.
+    pub fn follow_redirects(mut self, max: usize) -> Self {
+        self.max_redirects = max;
+        self
+    }
+
     /// Send a constructed request using this `TestClient`, and await the response.
-    pub fn perform(mut self, req: Request<Body>) -> Result<TestResponse> {
+    ///
+    /// When redirect following has been enabled via `follow_redirects`, the `3xx` chain is
+    /// traversed transparently and the returned `TestResponse` records the intermediate hops.
+    pub fn perform(mut self, req: Request<Body>) -> StdResult<TestResponse, TestRequestError> {
+        if self.max_redirects == 0 {
+            let response = self.issue(req)?;
+            return Ok(self.into_response(response, Vec::new()));
+        }
+
+        // Buffer the original body so it can be replayed across method-preserving redirects.
+        let (mut parts, body) = req.into_parts();
+        let mut body = self
+            .test_server
+            .run_future(body.concat2())
+            .map_err(TestRequestError::body_read)?
+            .to_vec();
+
+        let mut redirects = Vec::new();
+
+        loop {
+            let mut builder = Request::builder();
+            builder.method(parts.method.clone()).uri(parts.uri.clone());
+            for (name, value) in parts.headers.iter() {
+                builder.header(name.clone(), value.clone());
+            }
+            let req = builder.body(Body::from(body.clone())).unwrap();
+
+            let response = self.issue(req)?;
+
+            let location = match redirect_target(&response, &parts.uri) {
+                Some(location) => location,
+                None => return Ok(self.into_response(response, redirects)),
+            };
+
+            if redirects.len() >= self.max_redirects {
+                return Err(TestRequestError::too_many_redirects(self.max_redirects));
+            }
+
+            redirects.push((response.status(), location.clone()));
+
+            // 303 always continues as a bodyless GET; 307/308 (and the remaining 3xx here) keep the
+            // original method and body.
+            if response.status() == StatusCode::SEE_OTHER {
+                parts.method = Method::GET;
+                body = Vec::new();
+                // The original body is gone, so the headers that described it must not be replayed.
+                parts.headers.remove(CONTENT_LENGTH);
+                parts.headers.remove(CONTENT_TYPE);
+                parts.headers.remove(TRANSFER_ENCODING);
+            }
+            parts.uri = location;
+        }
+    }
+
+    /// Issues a single request on the internal runtime, mapping connection failures into a
+    /// classified `TestRequestError`.
+    fn issue(&mut self, req: Request<Body>) -> StdResult<Response<Body>, TestRequestError> {
         let req_future = self.client.request(req).map_err(|e| {
             warn!("Error from test client request {:?}", e);
-            failure::err_msg("request failed").compat()
+            TestRequestError::from(e)
         });
 
-        self.test_server
-            .run_request(req_future)
-            .map(|response| TestResponse {
-                response,
-                reader: Box::new(self.test_server.clone()),
-            })
+        self.test_server.run_request(req_future)
+    }
+
+    fn into_response(
+        &self,
+        response: Response<Body>,
+        redirects: Vec<(StatusCode, Uri)>,
+    ) -> TestResponse {
+        TestResponse {
+            response,
+            reader: Box::new(self.test_server.clone()),
+            redirects,
+        }
+    }
+
+    /// Sends a batch of constructed requests concurrently, and awaits all of the responses.
+    ///
+    /// The requests are issued together and driven on the shared runtime by
+    /// `TestServer::run_requests`, so they race against the handler rather than executing one at a
+    /// time. Responses are returned in submission order.
+    pub fn perform_all<I>(self, reqs: I) -> StdResult<Vec<TestResponse>, TestRequestError>
+    where
+        I: IntoIterator<Item = Request<Body>>,
+    {
+        let futures: Vec<_> = reqs
+            .into_iter()
+            .map(|req| self.client.request(req).map_err(TestRequestError::from))
+            .collect();
+
+        self.test_server.run_requests(futures)
     }
 }
 
+impl TestClient<TestConnect> {
+    /// Performs an HTTP Upgrade to the WebSocket protocol against the handler at `uri`.
+    ///
+    /// A request carrying the `Connection: Upgrade`, `Upgrade: websocket` and the mandatory
+    /// `Sec-WebSocket-Key`/`Sec-WebSocket-Version` headers is issued on the internal runtime. The
+    /// `101 Switching Protocols` status and the `Sec-WebSocket-Accept` hash are validated, then the
+    /// upgraded transport is recovered from Hyper's own upgrade machinery and returned as a
+    /// `TestWebSocket`, which can drive a bidirectional frame conversation with the handler.
+    pub fn upgrade(mut self, uri: &str) -> StdResult<TestWebSocket, TestRequestError> {
+        let key = base64::encode(&rand::random::<[u8; 16]>());
+
+        let req = Request::builder()
+            .method(Method::GET)
+            .uri(uri)
+            .header(CONNECTION, "Upgrade")
+            .header(UPGRADE, "websocket")
+            .header(SEC_WEBSOCKET_VERSION, "13")
+            .header(SEC_WEBSOCKET_KEY, key.as_str())
+            .body(Body::empty())
+            .unwrap();
+
+        let req_future = self.client.request(req).map_err(|e| {
+            warn!("Error from test client upgrade request {:?}", e);
+            TestRequestError::from(e)
+        });
+
+        let response = self.test_server.run_request(req_future)?;
+
+        if response.status() != StatusCode::SWITCHING_PROTOCOLS {
+            return Err(TestRequestError::other(failure::err_msg(format!(
+                "upgrade failed: expected 101 Switching Protocols, got {}",
+                response.status()
+            ))));
+        }
+
+        match response.headers().get(SEC_WEBSOCKET_ACCEPT) {
+            Some(accept) if accept.as_bytes() == accept_key(&key).as_bytes() => {}
+            Some(accept) => {
+                return Err(TestRequestError::other(failure::err_msg(format!(
+                    "Sec-WebSocket-Accept mismatch: {:?}",
+                    accept
+                ))));
+            }
+            None => {
+                return Err(TestRequestError::other(failure::err_msg(
+                    "upgrade response is missing Sec-WebSocket-Accept",
+                )));
+            }
+        }
+
+        // Recover the socket from Hyper's upgrade future rather than a duplicated fd, so that the
+        // single `Upgraded` returned here is the only reader of the connection and no handler
+        // frames are lost to Hyper's own read buffer.
+        let upgraded = self
+            .test_server
+            .run_future(response.into_body().on_upgrade())
+            .map_err(TestRequestError::other)?;
+
+        Ok(TestWebSocket {
+            stream: Some(upgraded),
+            test_server: self.test_server,
+        })
+    }
+}
+
+/// The WebSocket GUID concatenated with the client key to form the `Sec-WebSocket-Accept` hash, as
+/// defined by RFC 6455.
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Computes the `Sec-WebSocket-Accept` value for a given `Sec-WebSocket-Key`.
+fn accept_key(key: &str) -> String {
+    let mut sha1 = Sha1::new();
+    sha1.update(key.as_bytes());
+    sha1.update(WEBSOCKET_GUID.as_bytes());
+    base64::encode(&sha1.digest().bytes())
+}
+
+/// Returns the resolved target URI if `response` is a redirect carrying a usable `Location`,
+/// otherwise `None`.
+fn redirect_target(response: &Response<Body>, base: &Uri) -> Option<Uri> {
+    match response.status() {
+        StatusCode::MOVED_PERMANENTLY
+        | StatusCode::FOUND
+        | StatusCode::SEE_OTHER
+        | StatusCode::TEMPORARY_REDIRECT
+        | StatusCode::PERMANENT_REDIRECT => {}
+        _ => return None,
+    }
+
+    let location = response.headers().get(LOCATION)?.to_str().ok()?;
+    resolve_uri(base, location)
+}
+
+/// Resolves a `Location` header value against the URI of the request that produced it, inheriting
+/// the scheme and authority from `base` when the location is a relative reference.
+fn resolve_uri(base: &Uri, location: &str) -> Option<Uri> {
+    let target: Uri = location.parse().ok()?;
+    if target.scheme_part().is_some() {
+        return Some(target);
+    }
+
+    let mut parts = target.into_parts();
+    let base_parts = base.clone().into_parts();
+    parts.scheme = base_parts.scheme;
+    parts.authority = base_parts.authority;
+    if parts.path_and_query.is_none() {
+        parts.path_and_query = base_parts.path_and_query;
+    }
+    Uri::from_parts(parts).ok()
+}
+
+/// A single WebSocket frame exchanged with a handler through a `TestWebSocket`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WebSocketFrame {
+    /// A UTF-8 text frame (opcode `0x1`).
+    Text(String),
+    /// A binary frame (opcode `0x2`).
+    Binary(Vec<u8>),
+    /// A ping control frame (opcode `0x9`).
+    Ping(Vec<u8>),
+    /// A pong control frame (opcode `0xA`).
+    Pong(Vec<u8>),
+    /// A close control frame (opcode `0x8`).
+    Close,
+}
+
+/// A duplex handle to a handler that has been upgraded to the WebSocket protocol.
+///
+/// Frames are sent and received synchronously by driving the internal runtime, so tests can script
+/// a bidirectional conversation without managing an event loop of their own.
+pub struct TestWebSocket {
+    stream: Option<Upgraded>,
+    test_server: TestServer,
+}
+
+impl TestWebSocket {
+    /// Sends a single frame to the handler, masking the payload as required of a client.
+    pub fn send_frame(&mut self, frame: WebSocketFrame) -> StdResult<(), TestRequestError> {
+        let bytes = encode_client_frame(&frame);
+        let stream = self.stream.take().expect("TestWebSocket already closed");
+        let (stream, _) = self
+            .test_server
+            .run_future(write_all(stream, bytes))
+            .map_err(TestRequestError::other)?;
+        self.stream = Some(stream);
+        Ok(())
+    }
+
+    /// Receives a single frame from the handler, unmasking the payload if necessary.
+    pub fn read_frame(&mut self) -> StdResult<WebSocketFrame, TestRequestError> {
+        let stream = self.stream.take().expect("TestWebSocket already closed");
+
+        let (stream, header) = self
+            .test_server
+            .run_future(read_exact(stream, [0u8; 2]))
+            .map_err(TestRequestError::other)?;
+        let opcode = header[0] & 0x0f;
+        let masked = header[1] & 0x80 != 0;
+        let short_len = (header[1] & 0x7f) as usize;
+
+        let (stream, len) = match short_len {
+            126 => {
+                let (stream, buf) = self
+                    .test_server
+                    .run_future(read_exact(stream, [0u8; 2]))
+                    .map_err(TestRequestError::other)?;
+                (stream, u16::from_be_bytes(buf) as usize)
+            }
+            127 => {
+                let (stream, buf) = self
+                    .test_server
+                    .run_future(read_exact(stream, [0u8; 8]))
+                    .map_err(TestRequestError::other)?;
+                (stream, u64::from_be_bytes(buf) as usize)
+            }
+            n => (stream, n),
+        };
+
+        let (stream, mask) = if masked {
+            let (stream, mask) = self
+                .test_server
+                .run_future(read_exact(stream, [0u8; 4]))
+                .map_err(TestRequestError::other)?;
+            (stream, Some(mask))
+        } else {
+            (stream, None)
+        };
+
+        let (stream, mut payload) = self
+            .test_server
+            .run_future(read_exact(stream, vec![0u8; len]))
+            .map_err(TestRequestError::other)?;
+        self.stream = Some(stream);
+
+        if let Some(mask) = mask {
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+        }
+
+        let frame = match opcode {
+            0x1 => WebSocketFrame::Text(
+                String::from_utf8(payload).map_err(|e| TestRequestError::other(e.into()))?,
+            ),
+            0x2 => WebSocketFrame::Binary(payload),
+            0x8 => WebSocketFrame::Close,
+            0x9 => WebSocketFrame::Ping(payload),
+            0xa => WebSocketFrame::Pong(payload),
+            other => {
+                return Err(TestRequestError::other(failure::err_msg(format!(
+                    "unsupported WebSocket opcode: {:#x}",
+                    other
+                ))));
+            }
+        };
+        Ok(frame)
+    }
+}
+
+/// Encodes a frame as a masked client-to-server WebSocket message.
+fn encode_client_frame(frame: &WebSocketFrame) -> Vec<u8> {
+    let (opcode, payload): (u8, &[u8]) = match frame {
+        WebSocketFrame::Text(s) => (0x1, s.as_bytes()),
+        WebSocketFrame::Binary(b) => (0x2, b),
+        WebSocketFrame::Close => (0x8, &[]),
+        WebSocketFrame::Ping(b) => (0x9, b),
+        WebSocketFrame::Pong(b) => (0xa, b),
+    };
+
+    let mut out = Vec::with_capacity(payload.len() + 6);
+    // FIN bit set, single-fragment message.
+    out.push(0x80 | opcode);
+
+    let len = payload.len();
+    if len < 126 {
+        out.push(0x80 | len as u8);
+    } else if len <= u16::max_value() as usize {
+        out.push(0x80 | 126);
+        out.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        out.push(0x80 | 127);
+        out.extend_from_slice(&(len as u64).to_be_bytes());
+    }
+
+    let mask: [u8; 4] = rand::random();
+    out.extend_from_slice(&mask);
+    out.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+    out
+}
+
 trait BodyReader {
     /// Runs the underlying event loop until the response body has been fully read. An `Ok(_)`
     /// response holds a buffer containing all bytes of the response body.
-    fn read_body(&mut self, response: Response<Body>) -> Result<Vec<u8>>;
+    fn read_body(&mut self, response: Response<Body>) -> StdResult<Vec<u8>, TestRequestError>;
+
+    /// Like `read_body`, but aborts with `TestRequestError::BodyTooLarge` once the accumulated
+    /// body exceeds `max_bytes`, rather than the default `MAX_BODY_SIZE`.
+    fn read_body_with_limit(
+        &mut self,
+        response: Response<Body>,
+        max_bytes: usize,
+    ) -> StdResult<Vec<u8>, TestRequestError>;
+
+    /// Runs the event loop until the body is read, preserving the individual chunk boundaries as
+    /// they arrived off the wire.
+    fn read_body_chunks(
+        &mut self,
+        response: Response<Body>,
+    ) -> StdResult<Vec<Vec<u8>>, TestRequestError>;
 }
 
 /// Wrapping struct for the `Response` returned by a `TestClient`. Provides access to the
@@ -367,6 +860,7 @@ trait BodyReader {
 pub struct TestResponse {
     response: Response<Body>,
     reader: Box<BodyReader>,
+    redirects: Vec<(StatusCode, Uri)>,
 }
 
 impl Deref for TestResponse {
@@ -392,10 +886,43 @@ impl fmt::Debug for TestResponse {
 impl TestResponse {
     /// Awaits the body of the underlying `Response`, and returns it. This will cause the event
     /// loop to execute until the `Response` body has been fully read into the `Vec<u8>`.
-    pub fn read_body(mut self) -> Result<Vec<u8>> {
+    pub fn read_body(mut self) -> StdResult<Vec<u8>, TestRequestError> {
         self.reader.read_body(self.response)
     }
 
+    /// Awaits the body of the underlying `Response`, aborting with `TestRequestError::BodyTooLarge`
+    /// if it grows past `max_bytes`. The `Body` stream is folded chunk by chunk so a misbehaving or
+    /// unbounded handler body cannot exhaust memory in the test process; `read_body` does the same
+    /// with the fixed `MAX_BODY_SIZE` limit of 64 MiB.
+    pub fn read_body_with_limit(mut self, max_bytes: usize) -> StdResult<Vec<u8>, TestRequestError> {
+        self.reader.read_body_with_limit(self.response, max_bytes)
+    }
+
+    /// Returns the chain of intermediate `(status, uri)` pairs traversed while following
+    /// redirects. The slice is empty unless redirect following was enabled via
+    /// `TestClient::follow_redirects` and at least one hop was taken.
+    pub fn redirect_chain(&self) -> &[(StatusCode, Uri)] {
+        &self.redirects
+    }
+
+    /// Returns the response body as a `Stream` of its constituent chunks, driven through the
+    /// internal runtime. This preserves chunk boundaries, so tests can make assertions about
+    /// chunked or SSE-style responses rather than only the fully-collected buffer. The accumulated
+    /// body is capped at `MAX_BODY_SIZE`, beyond which the stream yields a `BodyTooLarge` error.
+    pub fn body_stream(self) -> impl Stream<Item = Vec<u8>, Error = TestRequestError> {
+        let TestResponse {
+            response,
+            mut reader,
+            redirects: _,
+        } = self;
+
+        match reader.read_body_chunks(response) {
+            Ok(chunks) => Box::new(stream::iter_ok(chunks))
+                as Box<dyn Stream<Item = Vec<u8>, Error = TestRequestError> + Send>,
+            Err(e) => Box::new(stream::once(Err(e))),
+        }
+    }
+
     /// Awaits the UTF-8 encoded body of the underlying `Response`, and returns the `String`. This
     /// will cause the event loop to execute until the `Response` body has been fully read and the
     /// `String` created.
@@ -406,8 +933,186 @@ impl TestResponse {
     }
 }
 
+/// The kind of failure represented by a `TestRequestError`.
+///
+/// Obtained via `TestRequestError::kind`; most callers will prefer the `is_*` inspectors.
+#[derive(Debug)]
+pub enum TestRequestErrorKind {
+    /// The request did not complete before the `TestServer` timeout elapsed.
+    Timeout {
+        /// The timeout that was exceeded.
+        elapsed: Duration,
+    },
+    /// The connection to the `TestServer` could not be established.
+    Connect,
+    /// The response body, or a request body buffered for redirect following, could not be read to
+    /// completion.
+    BodyRead,
+    /// The response body exceeded the maximum size permitted by the reader.
+    BodyTooLarge {
+        /// The byte limit that was exceeded.
+        limit: usize,
+    },
+    /// The redirect chain did not terminate within the configured hop limit.
+    TooManyRedirects {
+        /// The hop limit that was reached.
+        max: usize,
+    },
+    /// Any other failure produced while driving the request.
+    Other,
+}
+
+/// An opaque error returned by the `TestServer` request machinery.
+///
+/// Rather than collapsing every failure into a string, the cause is classified so that tests can
+/// branch on it with the `is_*` inspectors (`is_timeout`, `is_connect`, `is_body_read`) or match on
+/// `kind` directly. The originating error is retained and exposed through `Fail::cause`.
+#[derive(Debug)]
+pub struct TestRequestError {
+    kind: TestRequestErrorKind,
+    cause: failure::Error,
+}
+
+impl TestRequestError {
+    fn timeout(elapsed: Duration) -> TestRequestError {
+        TestRequestError {
+            kind: TestRequestErrorKind::Timeout { elapsed },
+            cause: failure::err_msg("timed out"),
+        }
+    }
+
+    fn connect(cause: failure::Error) -> TestRequestError {
+        TestRequestError {
+            kind: TestRequestErrorKind::Connect,
+            cause,
+        }
+    }
+
+    fn body_read(cause: failure::Error) -> TestRequestError {
+        TestRequestError {
+            kind: TestRequestErrorKind::BodyRead,
+            cause,
+        }
+    }
+
+    fn body_too_large(limit: usize) -> TestRequestError {
+        TestRequestError {
+            kind: TestRequestErrorKind::BodyTooLarge { limit },
+            cause: failure::err_msg(format!("response body exceeded {} bytes", limit)),
+        }
+    }
+
+    fn too_many_redirects(max: usize) -> TestRequestError {
+        TestRequestError {
+            kind: TestRequestErrorKind::TooManyRedirects { max },
+            cause: failure::err_msg(format!("exceeded {} redirects", max)),
+        }
+    }
+
+    fn other(cause: failure::Error) -> TestRequestError {
+        TestRequestError {
+            kind: TestRequestErrorKind::Other,
+            cause,
+        }
+    }
+
+    /// Returns the classification of this error.
+    pub fn kind(&self) -> &TestRequestErrorKind {
+        &self.kind
+    }
+
+    /// Returns `true` if the request exceeded the `TestServer` timeout.
+    pub fn is_timeout(&self) -> bool {
+        match self.kind {
+            TestRequestErrorKind::Timeout { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the connection to the `TestServer` could not be established.
+    pub fn is_connect(&self) -> bool {
+        match self.kind {
+            TestRequestErrorKind::Connect => true,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the response body, or a request body buffered for redirect following,
+    /// could not be read to completion.
+    pub fn is_body_read(&self) -> bool {
+        match self.kind {
+            TestRequestErrorKind::BodyRead => true,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the response body exceeded the reader's size limit.
+    pub fn is_body_too_large(&self) -> bool {
+        match self.kind {
+            TestRequestErrorKind::BodyTooLarge { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the redirect chain exceeded the configured hop limit.
+    pub fn is_too_many_redirects(&self) -> bool {
+        match self.kind {
+            TestRequestErrorKind::TooManyRedirects { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Returns how long the request ran before timing out, if this is a timeout error.
+    pub fn elapsed(&self) -> Option<Duration> {
+        match self.kind {
+            TestRequestErrorKind::Timeout { elapsed } => Some(elapsed),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TestRequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            TestRequestErrorKind::Timeout { elapsed } => {
+                write!(f, "test request timed out after {:?}", elapsed)
+            }
+            TestRequestErrorKind::Connect => {
+                write!(f, "failed to connect to the test server")
+            }
+            TestRequestErrorKind::BodyRead => {
+                write!(f, "failed to read the test response body")
+            }
+            TestRequestErrorKind::BodyTooLarge { limit } => {
+                write!(f, "test response body exceeded {} bytes", limit)
+            }
+            TestRequestErrorKind::TooManyRedirects { max } => {
+                write!(f, "redirect chain exceeded {} hops", max)
+            }
+            TestRequestErrorKind::Other => write!(f, "the test request failed"),
+        }
+    }
+}
+
+impl failure::Fail for TestRequestError {
+    fn cause(&self) -> Option<&dyn failure::Fail> {
+        Some(self.cause.as_fail())
+    }
+}
+
+impl From<hyper::Error> for TestRequestError {
+    fn from(e: hyper::Error) -> TestRequestError {
+        if e.is_connect() {
+            TestRequestError::connect(e.into())
+        } else {
+            TestRequestError::other(e.into())
+        }
+    }
+}
+
 /// `TestConnect` represents the connection between a test client and the `TestServer` instance
 /// that created it. This type should never be used directly.
+#[derive(Clone)]
 struct TestConnect {
     addr: SocketAddr,
 }
@@ -586,6 +1291,118 @@ mod tests {
         info!("{}:{}", file!(), line!());
     }
 
+    #[test]
+    fn computes_websocket_accept_key() {
+        // The key/accept pair from the handshake example in RFC 6455 section 1.3.
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn follows_redirects() {
+        let _ = ::pretty_env_logger::try_init_custom_env("GOTHAM_TEST_LOG");
+
+        fn handler(state: State) -> (State, Response<Body>) {
+            let path = Uri::borrow_from(&state).path().to_owned();
+            let response = match path.as_str() {
+                "/start" => Response::builder()
+                    .status(StatusCode::SEE_OTHER)
+                    .header(LOCATION, "/end")
+                    .body(Body::empty())
+                    .unwrap(),
+                "/end" => Response::builder()
+                    .status(StatusCode::OK)
+                    .body("done".into())
+                    .unwrap(),
+                "/loop" => Response::builder()
+                    .status(StatusCode::FOUND)
+                    .header(LOCATION, "/loop")
+                    .body(Body::empty())
+                    .unwrap(),
+                _ => unreachable!(),
+            };
+            (state, response)
+        }
+
+        let test_server = TestServer::new(|| Ok(handler)).unwrap();
+        let response = test_server
+            .client()
+            .follow_redirects(5)
+            .get("http://localhost/start")
+            .perform()
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.redirect_chain().len(), 1);
+        assert_eq!(response.redirect_chain()[0].0, StatusCode::SEE_OTHER);
+        assert_eq!(response.read_utf8_body().unwrap(), "done");
+
+        let test_server = TestServer::new(|| Ok(handler)).unwrap();
+        let err = test_server
+            .client()
+            .follow_redirects(2)
+            .get("http://localhost/loop")
+            .perform()
+            .unwrap_err();
+        assert!(err.is_too_many_redirects());
+    }
+
+    #[test]
+    fn limits_body_size() {
+        let _ = ::pretty_env_logger::try_init_custom_env("GOTHAM_TEST_LOG");
+        let new_service = || {
+            Ok(TestHandler {
+                response: "0123456789".to_owned(),
+            })
+        };
+
+        let test_server = TestServer::new(new_service).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+        let err = response.read_body_with_limit(4).unwrap_err();
+        assert!(err.is_body_too_large());
+
+        let test_server = TestServer::new(new_service).unwrap();
+        let response = test_server
+            .client()
+            .get("http://localhost/")
+            .perform()
+            .unwrap();
+        assert_eq!(response.read_body_with_limit(64).unwrap(), b"0123456789");
+    }
+
+    #[test]
+    fn runs_requests_concurrently() {
+        let _ = ::pretty_env_logger::try_init_custom_env("GOTHAM_TEST_LOG");
+        let new_service = || {
+            Ok(TestHandler {
+                response: "concurrent".to_owned(),
+            })
+        };
+
+        let test_server = TestServer::new(new_service).unwrap();
+        let reqs = (0..4).map(|_| {
+            Request::builder()
+                .method(Method::GET)
+                .uri("http://localhost/")
+                .body(Body::empty())
+                .unwrap()
+        });
+
+        let responses = test_server.client().perform_all(reqs).unwrap();
+
+        assert_eq!(responses.len(), 4);
+        for response in responses {
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.read_utf8_body().unwrap(), "concurrent");
+        }
+    }
+
     #[test]
     fn async_echo() {
         let _ = ::pretty_env_logger::try_init_custom_env("GOTHAM_TEST_LOG");